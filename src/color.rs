@@ -0,0 +1,79 @@
+//! Color parsing and formatting: hex notations and named ANSI colors.
+
+use std::fmt;
+
+use image::Rgba;
+
+/// The standard 16 ANSI terminal colors, by name. Also used as the `default`
+/// built-in [`crate::schemes::Scheme`].
+pub(crate) const NAMED_COLORS: [(&str, (u8, u8, u8)); 16] = [
+    ("black", (0x00, 0x00, 0x00)),
+    ("red", (0x80, 0x00, 0x00)),
+    ("green", (0x00, 0x80, 0x00)),
+    ("yellow", (0x80, 0x80, 0x00)),
+    ("blue", (0x00, 0x00, 0x80)),
+    ("magenta", (0x80, 0x00, 0x80)),
+    ("cyan", (0x00, 0x80, 0x80)),
+    ("white", (0xc0, 0xc0, 0xc0)),
+    ("bright-black", (0x80, 0x80, 0x80)),
+    ("bright-red", (0xff, 0x00, 0x00)),
+    ("bright-green", (0x00, 0xff, 0x00)),
+    ("bright-yellow", (0xff, 0xff, 0x00)),
+    ("bright-blue", (0x00, 0x00, 0xff)),
+    ("bright-magenta", (0xff, 0x00, 0xff)),
+    ("bright-cyan", (0x00, 0xff, 0xff)),
+    ("bright-white", (0xff, 0xff, 0xff)),
+];
+
+fn named_color(name: &str) -> Option<Rgba<u8>> {
+    NAMED_COLORS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|&(_, (r, g, b))| Rgba([r, g, b, 0xff]))
+}
+
+/// Parses a color from hex notation (`#RRGGBBAA`, `#RRGGBB`, `#RGBA`, or `#RGB`), or
+/// from one of the named ANSI colors (`black`, `red`, ..., `bright-white`).
+pub fn parse_color(x: &str) -> Option<Rgba<u8>> {
+    match x.strip_prefix('#') {
+        Some(hex) => parse_hex(hex),
+        None => named_color(x),
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Rgba<u8>> {
+    let channels: Vec<u8> = match hex.len() {
+        8 | 6 => {
+            let mut channels = (0..hex.len() / 2)
+                .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16))
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .ok()?;
+            if channels.len() == 3 {
+                channels.push(0xff);
+            }
+            channels
+        }
+        4 | 3 => {
+            let mut channels = hex
+                .chars()
+                .map(|c| c.to_digit(16).map(|n| (n as u8) << 4 | n as u8))
+                .collect::<Option<Vec<_>>>()?;
+            if channels.len() == 3 {
+                channels.push(0xff);
+            }
+            channels
+        }
+        _ => return None,
+    };
+
+    Some(Rgba([channels[0], channels[1], channels[2], channels[3]]))
+}
+
+pub struct FormattedColor(pub Rgba<u8>);
+
+impl fmt::Display for FormattedColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b, a] = self.0 .0;
+        f.write_fmt(format_args!("#{r:02x}{g:02x}{b:02x}{a:02x}"))
+    }
+}
@@ -0,0 +1,55 @@
+//! Reconstructing an image from a `.coe` file and the palette used to produce it.
+
+use std::collections::HashMap;
+
+use image::RgbaImage;
+
+use crate::{Error, Palette, Radix, Result};
+
+/// Parses a `.coe` file's `memory_initialization_radix` header and the index tokens
+/// making up its `memory_initialization_vector`, stripping the trailing `;`.
+fn parse_coe(coe: &str) -> Result<(Radix, Vec<&str>)> {
+    let radix_value: u32 = coe
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("memory_initialization_radix="))
+        .and_then(|value| value.trim_end_matches(';').trim().parse().ok())
+        .ok_or(Error::MissingRadixHeader)?;
+    let radix = Radix::parse(radix_value).ok_or(Error::InvalidRadixHeader(radix_value))?;
+
+    let vector_start = coe
+        .find("memory_initialization_vector=")
+        .map(|i| i + "memory_initialization_vector=".len())
+        .ok_or(Error::MissingVectorHeader)?;
+    let vector = coe[vector_start..]
+        .trim()
+        .strip_suffix(';')
+        .ok_or(Error::UnterminatedVector)?;
+
+    Ok((radix, vector.split_whitespace().collect()))
+}
+
+/// Reconstructs an image of size `width` by `height` from a `.coe` file's contents and
+/// the palette used to produce it, inverting the palette's color-to-index mapping.
+pub fn decode_image_from_coe(coe: &str, palette: &Palette, width: u32, height: u32) -> Result<RgbaImage> {
+    let (radix, tokens) = parse_coe(coe)?;
+
+    let expected = width as u64 * height as u64;
+    if tokens.len() as u64 != expected {
+        return Err(Error::UnexpectedIndexCount {
+            expected,
+            actual: tokens.len() as u64,
+        });
+    }
+
+    let colors_by_index: HashMap<i64, _> = palette.0.iter().map(|(&color, &index)| (index, color)).collect();
+
+    let mut img = RgbaImage::new(width, height);
+    for (i, token) in tokens.into_iter().enumerate() {
+        let index = i64::from_str_radix(token, radix.value())
+            .map_err(|_| Error::InvalidIndexToken(token.to_string()))?;
+        let &color = colors_by_index.get(&index).ok_or(Error::UnknownIndex(index))?;
+        img.put_pixel(i as u32 % width, i as u32 / width, color);
+    }
+
+    Ok(img)
+}
@@ -0,0 +1,354 @@
+//! Core palette and COE conversion logic for img2coe, usable independently of the CLI.
+
+pub mod color;
+pub mod decode;
+pub mod schemes;
+
+pub use color::{parse_color, FormattedColor};
+pub use decode::decode_image_from_coe;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, io,
+};
+
+use image::{DynamicImage, GenericImageView, Rgba};
+use toml::{Table, Value};
+
+/// Errors produced while building a [`Palette`] or converting an image to COE data.
+///
+/// This crate intentionally avoids `anyhow` so that downstream users aren't forced
+/// into a particular error-handling approach; `main.rs` wraps this in `anyhow` itself.
+#[derive(Debug)]
+pub enum Error {
+    /// An I/O operation failed.
+    Io(io::Error),
+    /// The palette TOML could not be parsed.
+    Toml(toml::de::Error),
+    /// The image could not be decoded.
+    Image(image::ImageError),
+    /// The palette TOML is missing its `[palette]` table.
+    MissingPaletteTable,
+    /// A palette key is not a valid color.
+    InvalidColor(String),
+    /// A palette value is not a valid integer index.
+    InvalidPaletteValue(String),
+    /// A pixel's color has no entry in the palette.
+    UnmappedColor(Rgba<u8>),
+    /// Nearest-color matching was requested but the palette has no entries to match
+    /// against.
+    EmptyPalette,
+    /// A palette index is negative, so it cannot be written in the declared radix.
+    NegativeIndex(Rgba<u8>, i64),
+    /// The `.coe` file has no `memory_initialization_radix` header.
+    MissingRadixHeader,
+    /// The `.coe` file declares a radix img2coe does not support.
+    InvalidRadixHeader(u32),
+    /// The `.coe` file has no `memory_initialization_vector` header.
+    MissingVectorHeader,
+    /// The `memory_initialization_vector` is not terminated with a `;`.
+    UnterminatedVector,
+    /// A token in the index stream is not a valid number in the declared radix.
+    InvalidIndexToken(String),
+    /// An index in the `.coe` file has no corresponding color in the palette.
+    UnknownIndex(i64),
+    /// The index stream doesn't have exactly `width * height` entries.
+    UnexpectedIndexCount { expected: u64, actual: u64 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io error: {e}"),
+            Error::Toml(e) => write!(f, "invalid palette toml: {e}"),
+            Error::Image(e) => write!(f, "invalid image: {e}"),
+            Error::MissingPaletteTable => write!(f, "expected to find 'palette' table on palette file"),
+            Error::InvalidColor(key) => write!(f, "invalid color: {key}"),
+            Error::InvalidPaletteValue(value) => write!(f, "value must be integer: {value}"),
+            Error::UnmappedColor(color) => write!(
+                f,
+                "could not continue: palette has no mapping for color \"{}\"",
+                FormattedColor(*color)
+            ),
+            Error::EmptyPalette => write!(f, "cannot match colors against an empty palette"),
+            Error::NegativeIndex(color, index) => write!(
+                f,
+                "palette index {index} for color \"{}\" is negative and cannot be written in the declared radix",
+                FormattedColor(*color)
+            ),
+            Error::MissingRadixHeader => write!(f, "coe file has no memory_initialization_radix header"),
+            Error::InvalidRadixHeader(value) => write!(f, "unsupported memory_initialization_radix: {value}"),
+            Error::MissingVectorHeader => write!(f, "coe file has no memory_initialization_vector header"),
+            Error::UnterminatedVector => write!(f, "memory_initialization_vector is not terminated with ';'"),
+            Error::InvalidIndexToken(token) => write!(f, "invalid index in coe file: {token}"),
+            Error::UnknownIndex(index) => write!(f, "palette has no color for index {index}"),
+            Error::UnexpectedIndexCount { expected, actual } => write!(
+                f,
+                "coe file has {actual} indices, expected {expected} (width * height)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Toml(e) => Some(e),
+            Error::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Error::Toml(e)
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Error::Image(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A mapping from RGBA colors to the numeric index written into COE memory.
+#[derive(Debug, Clone, Default)]
+pub struct Palette(pub HashMap<Rgba<u8>, i64>);
+
+impl Palette {
+    /// Parses a palette from the contents of a palette TOML file, as produced by the
+    /// `palette` command or written by hand.
+    pub fn from_toml_str(s: &str) -> Result<Self> {
+        let table = toml::from_str::<Table>(s)?;
+        let Some(palette_map) = table.get("palette").and_then(Value::as_table) else {
+            return Err(Error::MissingPaletteTable);
+        };
+
+        let mut palette = HashMap::new();
+        for (key, value) in palette_map {
+            let color = parse_color(key).ok_or_else(|| Error::InvalidColor(key.clone()))?;
+            let value = value
+                .as_integer()
+                .ok_or_else(|| Error::InvalidPaletteValue(value.to_string()))?;
+            palette.insert(color, value);
+        }
+
+        Ok(Palette(palette))
+    }
+
+    /// Extracts the distinct colors present in `img`, assigning each a sequential index.
+    pub fn extract_from_image(img: &DynamicImage) -> Self {
+        let mut colors = HashSet::new();
+        for (_, _, color) in img.pixels() {
+            colors.insert(color);
+        }
+
+        let palette = colors
+            .into_iter()
+            .enumerate()
+            .map(|(i, color)| (color, i as i64))
+            .collect();
+
+        Palette(palette)
+    }
+
+    /// Finds the palette entry closest to `color` by squared Euclidean distance over
+    /// the RGBA channels. Returns `None` if the palette has no entries.
+    pub fn nearest(&self, color: Rgba<u8>) -> Option<(Rgba<u8>, i64)> {
+        self.0
+            .iter()
+            .min_by_key(|(candidate, _)| squared_distance(color, **candidate))
+            .map(|(&candidate, &index)| (candidate, index))
+    }
+
+    /// Checks that every index in this palette fits the declared radix, i.e. is
+    /// non-negative.
+    fn validate_for_radix(&self) -> Result<()> {
+        for (&color, &index) in &self.0 {
+            if index < 0 {
+                return Err(Error::NegativeIndex(color, index));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn squared_distance(a: Rgba<u8>, b: Rgba<u8>) -> u32 {
+    a.0.iter()
+        .zip(b.0.iter())
+        .map(|(&a, &b)| {
+            let d = a as i32 - b as i32;
+            (d * d) as u32
+        })
+        .sum()
+}
+
+/// The numeric base used to format indices in a `.coe` file's
+/// `memory_initialization_vector`, matching its declared `memory_initialization_radix`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Radix {
+    Binary,
+    Decimal,
+    Hex,
+}
+
+impl Radix {
+    /// Parses a radix from its `memory_initialization_radix` value (2, 10, or 16).
+    pub fn parse(value: u32) -> Option<Self> {
+        match value {
+            2 => Some(Radix::Binary),
+            10 => Some(Radix::Decimal),
+            16 => Some(Radix::Hex),
+            _ => None,
+        }
+    }
+
+    /// The `memory_initialization_radix` value for this radix.
+    pub fn value(self) -> u32 {
+        match self {
+            Radix::Binary => 2,
+            Radix::Decimal => 10,
+            Radix::Hex => 16,
+        }
+    }
+
+    fn format_index(self, index: i64) -> String {
+        match self {
+            Radix::Binary => format!("{index:b}"),
+            Radix::Decimal => format!("{index}"),
+            Radix::Hex => format!("{index:x}"),
+        }
+    }
+}
+
+/// How to handle pixel colors that have no exact entry in the palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnmappedColorMode {
+    /// Fail as soon as an unmapped color is encountered.
+    #[default]
+    Error,
+    /// Quantize to the closest palette color.
+    Nearest,
+    /// Quantize to the closest palette color, propagating the quantization error to
+    /// not-yet-visited neighbors via Floyd–Steinberg dithering.
+    Dither,
+}
+
+/// Converts `img` into the whitespace-separated, semicolon-terminated index stream
+/// written into a `.coe` file's `memory_initialization_vector`, using `palette` to map
+/// colors to indices according to `mode`.
+pub fn convert_image_to_coe(
+    img: &DynamicImage,
+    palette: &Palette,
+    mode: UnmappedColorMode,
+    radix: Radix,
+) -> Result<String> {
+    if mode != UnmappedColorMode::Error && palette.0.is_empty() {
+        return Err(Error::EmptyPalette);
+    }
+    palette.validate_for_radix()?;
+
+    match mode {
+        UnmappedColorMode::Error => convert_exact(img, palette, radix),
+        UnmappedColorMode::Nearest => Ok(convert_nearest(img, palette, radix)),
+        UnmappedColorMode::Dither => Ok(convert_dithered(img, palette, radix)),
+    }
+}
+
+fn convert_exact(img: &DynamicImage, palette: &Palette, radix: Radix) -> Result<String> {
+    let mut out = String::new();
+    for (_, _, color) in img.pixels() {
+        let Some(&mapping) = palette.0.get(&color) else {
+            return Err(Error::UnmappedColor(color));
+        };
+        out.push_str(&radix.format_index(mapping));
+        out.push(' ');
+    }
+    out.push(';');
+
+    Ok(out)
+}
+
+fn convert_nearest(img: &DynamicImage, palette: &Palette, radix: Radix) -> String {
+    let mut out = String::new();
+    for (_, _, color) in img.pixels() {
+        let (_, mapping) = palette
+            .nearest(color)
+            .expect("palette emptiness is checked by the caller");
+        out.push_str(&radix.format_index(mapping));
+        out.push(' ');
+    }
+    out.push(';');
+
+    out
+}
+
+/// Quantizes `img` against `palette` while propagating quantization error to
+/// not-yet-visited neighbors with the classic Floyd–Steinberg weights:
+/// 7/16 to the right, 3/16 below-left, 5/16 below, and 1/16 below-right.
+fn convert_dithered(img: &DynamicImage, palette: &Palette, radix: Radix) -> String {
+    let (width, height) = img.dimensions();
+    let (width, height) = (width as i64, height as i64);
+
+    let mut rgb: Vec<[f32; 3]> = Vec::with_capacity((width * height) as usize);
+    let mut alpha: Vec<u8> = Vec::with_capacity((width * height) as usize);
+    for (_, _, Rgba([r, g, b, a])) in img.pixels() {
+        rgb.push([r as f32, g as f32, b as f32]);
+        alpha.push(a);
+    }
+
+    let mut out = String::new();
+    for y in 0..height {
+        for x in 0..width {
+            let idx = (y * width + x) as usize;
+            let [r, g, b] = rgb[idx];
+            let current = Rgba([
+                r.round().clamp(0.0, 255.0) as u8,
+                g.round().clamp(0.0, 255.0) as u8,
+                b.round().clamp(0.0, 255.0) as u8,
+                alpha[idx],
+            ]);
+
+            let (chosen, mapping) = palette
+                .nearest(current)
+                .expect("palette emptiness is checked by the caller");
+            out.push_str(&radix.format_index(mapping));
+            out.push(' ');
+
+            let error = [
+                r - chosen.0[0] as f32,
+                g - chosen.0[1] as f32,
+                b - chosen.0[2] as f32,
+            ];
+
+            let mut propagate = |dx: i64, dy: i64, weight: f32| {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || nx >= width || ny < 0 || ny >= height {
+                    return;
+                }
+                let nidx = (ny * width + nx) as usize;
+                for c in 0..3 {
+                    rgb[nidx][c] = (rgb[nidx][c] + error[c] * weight).clamp(0.0, 255.0);
+                }
+            };
+
+            propagate(1, 0, 7.0 / 16.0);
+            propagate(-1, 1, 3.0 / 16.0);
+            propagate(0, 1, 5.0 / 16.0);
+            propagate(1, 1, 1.0 / 16.0);
+        }
+    }
+    out.push(';');
+
+    out
+}
@@ -1,14 +1,15 @@
 use std::{
-    collections::{HashSet, HashMap},
     fs,
-    io::{Read, Write, self},
-    path::{PathBuf, Display},
+    io::{self, Read, Write},
+    path::PathBuf,
 };
 
 use anyhow::bail;
-use clap::{Parser, Subcommand};
-use image::{GenericImageView, Rgba};
-use toml::{Table, Value};
+use clap::{ArgGroup, Parser, Subcommand};
+use img2coe::{
+    convert_image_to_coe, decode_image_from_coe, schemes::Scheme, FormattedColor, Palette, Radix,
+    UnmappedColorMode,
+};
 
 #[derive(Debug, Parser)] // requires `derive` feature
 #[command(name = "img2coe")]
@@ -20,42 +21,54 @@ struct Cli {
 
 #[derive(Debug, Subcommand)]
 enum Commands {
+    #[command(group(ArgGroup::new("palette_source").args(["palette", "scheme"]).required(true)))]
     Convert {
         /// The image to convert
         image: PathBuf,
-        /// The palette to use
+        /// The palette file to use
         #[arg(short)]
-        palette: PathBuf,
+        palette: Option<PathBuf>,
+        /// A built-in scheme to use instead of a palette file; see the `palettes`
+        /// command for the list of available names
+        #[arg(long)]
+        scheme: Option<String>,
+        /// Quantize colors with no exact palette entry to the closest match, instead
+        /// of failing
+        #[arg(long)]
+        nearest: bool,
+        /// Like `--nearest`, but propagate the quantization error to neighboring
+        /// pixels via Floyd–Steinberg dithering
+        #[arg(long, conflicts_with = "nearest")]
+        dither: bool,
+        /// The numeric base to write indices in (2, 10, or 16), matching the declared
+        /// memory_initialization_radix
+        #[arg(long, default_value_t = 16)]
+        radix: u32,
     },
     Palette {
         /// The image to extract the palette from
         image: PathBuf,
     },
-}
-
-fn parse_color(x: &str) -> Option<Rgba<u8>> {
-    if !x.starts_with("#") {
-        return None;
-    }
-    let x = &x[1..];
-    if x.len() != 8 {
-        return None;
-    }
-    let r = u8::from_str_radix(&x[0..=1], 16).ok()?;
-    let g = u8::from_str_radix(&x[2..=3], 16).ok()?;
-    let b = u8::from_str_radix(&x[4..=5], 16).ok()?;
-    let a = u8::from_str_radix(&x[6..=7], 16).ok()?;
-
-    Some(Rgba([r, g, b, a]))
-}
-
-struct FormattedColor(Rgba<u8>);
-
-impl std::fmt::Display for FormattedColor {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let [r, g, b, a] = self.0.0;
-        f.write_fmt(format_args!("#{r:2x}{g:2x}{b:2x}{a:2x}"))
-    }
+    /// List the built-in palettes available via `--scheme`
+    Palettes,
+    /// Reconstruct an image from a .coe file and the palette used to produce it
+    Decode {
+        /// The .coe file to decode
+        coe: PathBuf,
+        /// The palette used to produce it
+        #[arg(short)]
+        palette: PathBuf,
+        /// The output image's width in pixels
+        #[arg(long)]
+        width: u32,
+        /// The output image's height in pixels
+        #[arg(long)]
+        height: u32,
+        /// Where to write the reconstructed image (defaults to the .coe path with a
+        /// .png extension)
+        #[arg(short)]
+        output: Option<PathBuf>,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
@@ -64,56 +77,83 @@ fn main() -> anyhow::Result<()> {
     match args.command {
         Commands::Palette { image } => {
             let img = ::image::open(&image)?;
-            let mut palette = HashSet::new();
-
-            for (_, _, color) in img.pixels() {
-                palette.insert(color);
-            }
+            let palette = Palette::extract_from_image(&img);
 
             let mut file = io::BufWriter::new(fs::File::create(image.with_extension("palette.toml"))?);
             let template = include_str!("palette_template.toml")
                 .replace("{VERSION}", env!("CARGO_PKG_VERSION"));
-            file.write(template.as_bytes())?;
+            file.write_all(template.as_bytes())?;
 
-            for (i, color) in palette.into_iter().enumerate() {
-let color = FormattedColor(color);
-                file.write(format!("\"{color}\" = {i}\n").as_bytes())?;
+            for (color, i) in &palette.0 {
+                let color = FormattedColor(*color);
+                file.write_all(format!("\"{color}\" = {i}\n").as_bytes())?;
             }
         }
-        Commands::Convert { image, palette } => {
-            let mut palette_file = fs::File::open(palette)?;
-            let mut palette_str = String::new();
-            palette_file.read_to_string(&mut palette_str)?;
-            let table = toml::from_str::<Table>(&palette_str)?;
-            let Some(palette_map) = table.get("palette").and_then(Value::as_table) else {
-                bail!("expected to find 'palette' table on palette file")
+        Commands::Convert {
+            image,
+            palette,
+            scheme,
+            nearest,
+            dither,
+            radix,
+        } => {
+            let Some(radix) = Radix::parse(radix) else {
+                bail!("invalid radix: {radix} (expected 2, 10, or 16)")
             };
-            let mut palette = HashMap::new();
-            for (key, value) in palette_map {
-                let Some(color) = parse_color(key) else {
-                    bail!("invalid color: {key}")
-                };
-                let Some(value) = value.as_integer() else {
-                    bail!("value must be integer: {value}")
+
+            let palette = if let Some(scheme) = scheme {
+                let Some(scheme) = Scheme::parse(&scheme) else {
+                    bail!("unknown scheme: {scheme} (see the `palettes` command for valid names)")
                 };
-                palette.insert(color, value);
-            }
+                scheme.palette()
+            } else {
+                let mut palette_file = fs::File::open(palette.expect("enforced by palette_source group"))?;
+                let mut palette_str = String::new();
+                palette_file.read_to_string(&mut palette_str)?;
+                Palette::from_toml_str(&palette_str)?
+            };
 
-            let mut coe_file = io::BufWriter::new(fs::File::create(image.with_extension("coe"))?);
-            let template = include_str!("coe_template.coe")
-                .replace("{VERSION}", env!("CARGO_PKG_VERSION"));
-            coe_file.write(template.as_bytes())?;
+            let mode = if dither {
+                UnmappedColorMode::Dither
+            } else if nearest {
+                UnmappedColorMode::Nearest
+            } else {
+                UnmappedColorMode::Error
+            };
 
             let img = ::image::open(&image)?;
-            for (_, _, color) in img.pixels() {
-                if let Some(&mapping) = palette.get(&color) {
-                    coe_file.write(format!("{mapping:x} ").as_bytes())?;
-                } else {
-                    bail!("could not continue: palette has no mapping for color \"{}\"", FormattedColor(color));
-                }
+            let body = convert_image_to_coe(&img, &palette, mode, radix)?;
+
+            let mut coe_file = io::BufWriter::new(fs::File::create(image.with_extension("coe"))?);
+            let template = include_str!("coe_template.coe")
+                .replace("{VERSION}", env!("CARGO_PKG_VERSION"))
+                .replace("{RADIX}", &radix.value().to_string());
+            coe_file.write_all(template.as_bytes())?;
+            coe_file.write_all(body.as_bytes())?;
+        }
+        Commands::Palettes => {
+            for scheme in Scheme::ALL {
+                println!("{}", scheme.name());
             }
+        }
+        Commands::Decode {
+            coe,
+            palette,
+            width,
+            height,
+            output,
+        } => {
+            let mut coe_str = String::new();
+            fs::File::open(&coe)?.read_to_string(&mut coe_str)?;
+
+            let mut palette_str = String::new();
+            fs::File::open(palette)?.read_to_string(&mut palette_str)?;
+            let palette = Palette::from_toml_str(&palette_str)?;
+
+            let img = decode_image_from_coe(&coe_str, &palette, width, height)?;
 
-            coe_file.write(";".as_bytes())?;
+            let output = output.unwrap_or_else(|| coe.with_extension("png"));
+            img.save(output)?;
         }
     }
 
@@ -0,0 +1,106 @@
+//! Built-in named palettes, selectable with `--scheme` without needing a palette file.
+
+use std::collections::HashMap;
+
+use image::Rgba;
+
+use crate::{color::NAMED_COLORS, Palette};
+
+/// A built-in color scheme, selectable by name via `--scheme`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// The standard 16-color ANSI terminal palette.
+    Default,
+    /// The Solarized Dark palette.
+    SolarizedDark,
+    /// The Solarized Light palette.
+    SolarizedLight,
+}
+
+impl Scheme {
+    /// All built-in schemes, in the order shown by the `palettes` command.
+    pub const ALL: [Scheme; 3] = [Scheme::Default, Scheme::SolarizedDark, Scheme::SolarizedLight];
+
+    /// The name used to select this scheme with `--scheme`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Scheme::Default => "default",
+            Scheme::SolarizedDark => "solarized-dark",
+            Scheme::SolarizedLight => "solarized-light",
+        }
+    }
+
+    /// Parses a scheme by its `--scheme` name.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scheme| scheme.name() == name)
+    }
+
+    fn colors(self) -> &'static [(u8, u8, u8)] {
+        match self {
+            Scheme::Default => &ANSI_16,
+            Scheme::SolarizedDark => &SOLARIZED_DARK,
+            Scheme::SolarizedLight => &SOLARIZED_LIGHT,
+        }
+    }
+
+    /// Builds the palette for this scheme, assigning indices in table order.
+    pub fn palette(self) -> Palette {
+        let palette = self
+            .colors()
+            .iter()
+            .enumerate()
+            .map(|(i, &(r, g, b))| (Rgba([r, g, b, 0xff]), i as i64))
+            .collect::<HashMap<_, _>>();
+
+        Palette(palette)
+    }
+}
+
+/// The standard 16-color ANSI palette, in the same order as [`NAMED_COLORS`].
+const ANSI_16: [(u8, u8, u8); 16] = {
+    let mut colors = [(0u8, 0u8, 0u8); 16];
+    let mut i = 0;
+    while i < NAMED_COLORS.len() {
+        colors[i] = NAMED_COLORS[i].1;
+        i += 1;
+    }
+    colors
+};
+
+const SOLARIZED_DARK: [(u8, u8, u8); 16] = [
+    (0x07, 0x36, 0x42), // base02
+    (0xdc, 0x32, 0x2f), // red
+    (0x85, 0x99, 0x00), // green
+    (0xb5, 0x89, 0x00), // yellow
+    (0x26, 0x8b, 0xd2), // blue
+    (0xd3, 0x36, 0x82), // magenta
+    (0x2a, 0xa1, 0x98), // cyan
+    (0xee, 0xe8, 0xd5), // base2
+    (0x00, 0x2b, 0x36), // base03
+    (0xcb, 0x4b, 0x16), // orange
+    (0x58, 0x6e, 0x75), // base01
+    (0x65, 0x7b, 0x83), // base00
+    (0x83, 0x94, 0x96), // base0
+    (0x6c, 0x71, 0xc4), // violet
+    (0x93, 0xa1, 0xa1), // base1
+    (0xfd, 0xf6, 0xe3), // base3
+];
+
+const SOLARIZED_LIGHT: [(u8, u8, u8); 16] = [
+    (0xee, 0xe8, 0xd5), // base2
+    (0xdc, 0x32, 0x2f), // red
+    (0x85, 0x99, 0x00), // green
+    (0xb5, 0x89, 0x00), // yellow
+    (0x26, 0x8b, 0xd2), // blue
+    (0xd3, 0x36, 0x82), // magenta
+    (0x2a, 0xa1, 0x98), // cyan
+    (0x07, 0x36, 0x42), // base02
+    (0xfd, 0xf6, 0xe3), // base3
+    (0xcb, 0x4b, 0x16), // orange
+    (0x93, 0xa1, 0xa1), // base1
+    (0x83, 0x94, 0x96), // base0
+    (0x65, 0x7b, 0x83), // base00
+    (0x6c, 0x71, 0xc4), // violet
+    (0x58, 0x6e, 0x75), // base01
+    (0x00, 0x2b, 0x36), // base03
+];